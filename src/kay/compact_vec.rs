@@ -3,7 +3,12 @@ use super::pointer_to_maybe_compact::PointerToMaybeCompact;
 use super::compact::Compact;
 use ::std::marker::PhantomData;
 use ::std::ptr;
+use ::std::mem::{ManuallyDrop, MaybeUninit};
 use ::std::ops::{Deref, DerefMut};
+#[cfg(feature = "serde")]
+use ::serde::{Serialize, Serializer, Deserialize, Deserializer};
+#[cfg(feature = "serde")]
+use ::serde::de::{Visitor, SeqAccess};
 
 pub struct CompactVec <T, A: Allocator = DefaultHeap> {
     ptr: PointerToMaybeCompact<T>,
@@ -17,11 +22,29 @@ impl<T, A: Allocator> CompactVec<T, A> {
         self.len
     }
 
+    fn is_zst() -> bool {
+        ::std::mem::size_of::<T>() == 0
+    }
+
+    // a ZST never needs real storage, so give it a dangling-but-aligned
+    // pointer and treat its capacity as unbounded, as `RawVec` does
+    fn dangling_ptr() -> *mut T {
+        ::std::ptr::NonNull::dangling().as_ptr()
+    }
+
     pub fn new() -> CompactVec<T, A> {
+        let mut ptr = PointerToMaybeCompact::default();
+        let cap = if Self::is_zst() {
+            ptr.set_to_free(Self::dangling_ptr());
+            usize::max_value()
+        } else {
+            0
+        };
+
         CompactVec {
-            ptr: PointerToMaybeCompact::default(),
+            ptr: ptr,
             len: 0,
-            cap: 0,
+            cap: cap,
             _alloc: PhantomData
         }
     }
@@ -30,11 +53,15 @@ impl<T, A: Allocator> CompactVec<T, A> {
         let mut vec = CompactVec {
             ptr: PointerToMaybeCompact::default(),
             len: 0,
-            cap: cap,
+            cap: if Self::is_zst() {usize::max_value()} else {cap},
             _alloc: PhantomData
         };
 
-        vec.ptr.set_to_free(A::allocate::<T>(cap));
+        if Self::is_zst() {
+            vec.ptr.set_to_free(Self::dangling_ptr());
+        } else {
+            vec.ptr.set_to_free(A::allocate::<T>(cap));
+        }
         vec
     }
 
@@ -54,19 +81,58 @@ impl<T, A: Allocator> CompactVec<T, A> {
         if !self.ptr.is_compact() {
             unsafe {
                 ptr::drop_in_place(&mut self[..]);
+                if !Self::is_zst() {
+                    A::deallocate(self.ptr.mut_ptr(), self.cap);
+                }
+            }
+        }
+    }
+
+    // after copying the live elements into a new buffer, release the old
+    // one without running element destructors: the elements were moved,
+    // not dropped, so `maybe_drop`'s `drop_in_place` would tear down
+    // anything they own (e.g. a nested `CompactVec`'s backing buffer)
+    // while the new buffer still holds an identical, now-dangling copy
+    fn free_old_buffer(&mut self) {
+        if !self.ptr.is_compact() {
+            unsafe {
                 A::deallocate(self.ptr.mut_ptr(), self.cap);
             }
         }
     }
 
     fn double_buf(&mut self) {
+        // ZSTs report an effectively infinite capacity up front and are
+        // never (re)allocated, so this should never be reached for them
+        debug_assert!(!Self::is_zst());
+
         let new_cap = if self.cap == 0 {1} else {self.cap * 2};
         let new_ptr = A::allocate::<T>(new_cap);
 
         unsafe {
             ptr::copy_nonoverlapping(self.ptr.ptr(), new_ptr, self.len);
         }
-        self.maybe_drop();
+        self.free_old_buffer();
+        self.ptr.set_to_free(new_ptr);
+        self.cap = new_cap;
+    }
+
+    /// Grows the backing storage in a single allocation, so callers building
+    /// up a vector in bulk don't pay for repeated `double_buf` reallocations.
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.len + additional;
+
+        if required <= self.cap || Self::is_zst() {
+            return;
+        }
+
+        let new_cap = required.next_power_of_two();
+        let new_ptr = A::allocate::<T>(new_cap);
+
+        unsafe {
+            ptr::copy_nonoverlapping(self.ptr.ptr(), new_ptr, self.len);
+        }
+        self.free_old_buffer();
         self.ptr.set_to_free(new_ptr);
         self.cap = new_cap;
     }
@@ -111,8 +177,112 @@ impl<T, A: Allocator> CompactVec<T, A> {
     }
 
     pub fn clear(&mut self) {
-        // TODO: Drop?
-        self.len = 0;
+        self.truncate(0);
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        unsafe {
+            if len < self.len {
+                let s = self.get_unchecked_mut(len..self.len) as *mut _;
+                self.len = len;
+                ptr::drop_in_place(s);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len;
+        assert!(index < len);
+
+        unsafe {
+            let result;
+            {
+                let p = self.as_mut_ptr().offset(index as isize);
+                result = ptr::read(p);
+                ptr::copy(p.offset(1), p, len - index - 1);
+            }
+            self.len = len - 1;
+            result
+        }
+    }
+
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let len = self.len;
+        assert!(index < len);
+
+        unsafe {
+            let last = ptr::read(self.get_unchecked(len - 1));
+            let hole = self.get_unchecked_mut(index) as *mut T;
+            self.len = len - 1;
+            ptr::replace(hole, last)
+        }
+    }
+
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let len = self.len;
+        let mut deleted = 0;
+
+        {
+            let v = &mut self[..];
+
+            for i in 0..len {
+                if !f(&v[i]) {
+                    deleted += 1;
+                } else if deleted > 0 {
+                    v.swap(i - deleted, i);
+                }
+            }
+        }
+
+        if deleted > 0 {
+            self.truncate(len - deleted);
+        }
+    }
+
+    /// Moves all of `other`'s elements into `self`, leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        self.reserve(other.len());
+
+        unsafe {
+            let end = self.as_mut_ptr().offset(self.len as isize);
+            ptr::copy_nonoverlapping(other.as_ptr(), end, other.len());
+        }
+
+        self.len += other.len;
+        other.len = 0;
+    }
+}
+
+impl<T: Copy, A: Allocator> CompactVec<T, A> {
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        self.reserve(other.len());
+
+        unsafe {
+            let end = self.as_mut_ptr().offset(self.len as isize);
+            ptr::copy_nonoverlapping(other.as_ptr(), end, other.len());
+        }
+
+        self.len += other.len();
+    }
+}
+
+impl<T, A: Allocator> Extend<T> for CompactVec<T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let (lower_bound, _) = iter.size_hint();
+        self.reserve(lower_bound);
+
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
+
+impl<T, A: Allocator> FromIterator<T> for CompactVec<T, A> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = CompactVec::new();
+        vec.extend(iter);
+        vec
     }
 }
 
@@ -128,8 +298,61 @@ impl<T, A: Allocator> From<Vec<T>> for CompactVec<T, A> {
             ptr: PointerToMaybeCompact::new_free(p),
             len: len,
             cap: cap,
-            _alloc: PhantomData 
+            _alloc: PhantomData
+        }
+    }
+}
+
+impl<T: Clone, A: Allocator> Clone for CompactVec<T, A> {
+    fn clone(&self) -> Self {
+        if self.len == 0 {
+            return CompactVec::new();
+        }
+
+        // drops and deallocates the already-cloned prefix if `T::clone`
+        // panics partway through, so a failed clone never leaks
+        struct DropGuard<T, A: Allocator> {
+            ptr: *mut T,
+            len: usize,
+            cap: usize,
+            _alloc: PhantomData<A>
+        }
+
+        impl<T, A: Allocator> Drop for DropGuard<T, A> {
+            fn drop(&mut self) {
+                unsafe {
+                    ptr::drop_in_place(::std::slice::from_raw_parts_mut(self.ptr, self.len));
+                    if ::std::mem::size_of::<T>() != 0 {
+                        A::deallocate(self.ptr, self.cap);
+                    }
+                }
+            }
+        }
+
+        let new_ptr = if Self::is_zst() {
+            Self::dangling_ptr()
+        } else {
+            A::allocate::<T>(self.len)
+        };
+        let mut guard = DropGuard {ptr: new_ptr, len: 0, cap: self.len, _alloc: PhantomData::<A>};
+
+        for elem in self.iter() {
+            unsafe {
+                ptr::write(guard.ptr.offset(guard.len as isize), elem.clone());
+            }
+            guard.len += 1;
         }
+
+        ::std::mem::forget(guard);
+
+        let mut vec = CompactVec {
+            ptr: PointerToMaybeCompact::default(),
+            len: self.len,
+            cap: if Self::is_zst() {usize::max_value()} else {self.len},
+            _alloc: PhantomData
+        };
+        vec.ptr.set_to_free(new_ptr);
+        vec
     }
 }
 
@@ -175,19 +398,566 @@ impl<'a, T, A: Allocator> IntoIterator for &'a mut CompactVec<T, A> {
     }
 }
 
-impl<T: Copy, A: Allocator> Compact for CompactVec<T, A> {
+impl<T: Compact, A: Allocator> Compact for CompactVec<T, A> {
     fn is_still_compact(&self) -> bool {
-        self.ptr.is_compact()
+        self.ptr.is_compact() && self.iter().all(|elem| elem.is_still_compact())
     }
 
     fn dynamic_size_bytes(&self) -> usize {
         self.cap * ::std::mem::size_of::<T>()
+            + self.iter().map(|elem| elem.dynamic_size_bytes()).sum::<usize>()
     }
 
     unsafe fn compact_from(&mut self, source: &Self, new_dynamic_part: *mut u8) {
         self.len = source.len;
         self.cap = source.cap;
         self.ptr.set_to_compact(new_dynamic_part as *mut T);
-        ptr::copy_nonoverlapping(source.ptr.ptr(), self.ptr.mut_ptr(), self.len);
+
+        // each element's own dynamic part is stored after the header array,
+        // packed directly after the dynamic part of the previous element
+        let mut offset = self.cap * ::std::mem::size_of::<T>();
+
+        for (i, source_elem) in source.iter().enumerate() {
+            let elem_dynamic_part = new_dynamic_part.offset(offset as isize);
+            (*self.ptr.mut_ptr().offset(i as isize)).compact_from(source_elem, elem_dynamic_part);
+            offset += source_elem.dynamic_size_bytes();
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize, A: Allocator> Serialize for CompactVec<T, A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Deserialize<'de>, A: Allocator> Deserialize<'de> for CompactVec<T, A> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CompactVecVisitor<T, A: Allocator> {
+            _marker: PhantomData<(T, A)>
+        }
+
+        impl<'de, T: Deserialize<'de>, A: Allocator> Visitor<'de> for CompactVecVisitor<T, A> {
+            type Value = CompactVec<T, A>;
+
+            fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                formatter.write_str("a sequence")
+            }
+
+            fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Self::Value, S::Error> {
+                let mut vec = CompactVec::new();
+                if let Some(lower_bound) = seq.size_hint() {
+                    vec.reserve(lower_bound);
+                }
+
+                while let Some(elem) = seq.next_element()? {
+                    vec.push(elem);
+                }
+
+                Ok(vec)
+            }
+        }
+
+        deserializer.deserialize_seq(CompactVecVisitor {_marker: PhantomData})
+    }
+}
+
+/// A `CompactVec` variant that stores up to `N` elements inline, only
+/// spilling into the `Allocator` once it grows past that. Like
+/// `CompactVec`, it can also be flattened into a contiguous compact
+/// region, at which point it behaves exactly like a compacted
+/// `CompactVec` of length `self.len()`.
+pub struct SmallCompactVec<T, const N: usize, A: Allocator = DefaultHeap> {
+    inline: ManuallyDrop<[MaybeUninit<T>; N]>,
+    is_inline: bool,
+    ptr: PointerToMaybeCompact<T>,
+    len: usize,
+    cap: usize,
+    _alloc: PhantomData<A>
+}
+
+impl<T, const N: usize, A: Allocator> SmallCompactVec<T, N, A> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_zst() -> bool {
+        ::std::mem::size_of::<T>() == 0
+    }
+
+    fn dangling_ptr() -> *mut T {
+        ::std::ptr::NonNull::dangling().as_ptr()
+    }
+
+    pub fn new() -> SmallCompactVec<T, N, A> {
+        // a ZST needs no storage at all, inline or heap, so skip the
+        // inline slots entirely and give it the same unbounded, never
+        // (re)allocated capacity `CompactVec` uses for ZSTs
+        if Self::is_zst() {
+            let mut ptr = PointerToMaybeCompact::default();
+            ptr.set_to_free(Self::dangling_ptr());
+
+            return SmallCompactVec {
+                inline: ManuallyDrop::new(unsafe { MaybeUninit::uninit().assume_init() }),
+                is_inline: false,
+                ptr: ptr,
+                len: 0,
+                cap: usize::max_value(),
+                _alloc: PhantomData
+            };
+        }
+
+        SmallCompactVec {
+            inline: ManuallyDrop::new(unsafe { MaybeUninit::uninit().assume_init() }),
+            is_inline: true,
+            ptr: PointerToMaybeCompact::default(),
+            len: 0,
+            cap: N,
+            _alloc: PhantomData
+        }
+    }
+
+    fn as_ptr(&self) -> *const T {
+        if self.is_inline {
+            self.inline.as_ptr() as *const T
+        } else {
+            self.ptr.ptr()
+        }
+    }
+
+    fn as_mut_ptr_raw(&mut self) -> *mut T {
+        if self.is_inline {
+            self.inline.as_mut_ptr() as *mut T
+        } else {
+            self.ptr.mut_ptr()
+        }
+    }
+
+    fn maybe_drop(&mut self) {
+        if self.is_inline || !self.ptr.is_compact() {
+            unsafe {
+                ptr::drop_in_place(&mut self[..]);
+                if !self.is_inline && !Self::is_zst() {
+                    A::deallocate(self.ptr.mut_ptr(), self.cap);
+                }
+            }
+        }
+    }
+
+    // migrates from inline storage to a heap buffer on first overflow,
+    // or grows the existing heap buffer, exactly like `CompactVec::double_buf`
+    fn grow(&mut self) {
+        // ZSTs are constructed directly in the unbounded, never-inline
+        // state in `new`, so growth should never be reached for them
+        debug_assert!(!Self::is_zst());
+
+        let new_cap = if self.cap == 0 {1} else {self.cap * 2};
+        let new_ptr = A::allocate::<T>(new_cap);
+
+        unsafe {
+            ptr::copy_nonoverlapping(self.as_ptr(), new_ptr, self.len);
+        }
+
+        if !self.is_inline && !self.ptr.is_compact() {
+            unsafe {
+                A::deallocate(self.ptr.mut_ptr(), self.cap);
+            }
+        }
+
+        self.is_inline = false;
+        self.ptr.set_to_free(new_ptr);
+        self.cap = new_cap;
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.grow();
+        }
+
+        unsafe {
+            let end = self.as_mut_ptr_raw().offset(self.len as isize);
+            ptr::write(end, value);
+            self.len += 1;
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            unsafe {
+                self.len -= 1;
+                Some(ptr::read(self.get_unchecked(self.len())))
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        unsafe {
+            if len < self.len {
+                let s = self.get_unchecked_mut(len..self.len) as *mut _;
+                self.len = len;
+                ptr::drop_in_place(s);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len;
+        assert!(index < len);
+
+        unsafe {
+            let result;
+            {
+                let p = self.as_mut_ptr_raw().offset(index as isize);
+                result = ptr::read(p);
+                ptr::copy(p.offset(1), p, len - index - 1);
+            }
+            self.len = len - 1;
+            result
+        }
+    }
+}
+
+impl<T, const N: usize, A: Allocator> Drop for SmallCompactVec<T, N, A> {
+    fn drop(&mut self) {
+        self.maybe_drop();
+    }
+}
+
+impl<T, const N: usize, A: Allocator> Deref for SmallCompactVec<T, N, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe {
+            ::std::slice::from_raw_parts(self.as_ptr(), self.len)
+        }
+    }
+}
+
+impl<T, const N: usize, A: Allocator> DerefMut for SmallCompactVec<T, N, A> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe {
+            ::std::slice::from_raw_parts_mut(self.as_mut_ptr_raw(), self.len)
+        }
+    }
+}
+
+impl<'a, T, const N: usize, A: Allocator> IntoIterator for &'a SmallCompactVec<T, N, A> {
+    type Item = &'a T;
+    type IntoIter = ::std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.deref().into_iter()
+    }
+}
+
+impl<'a, T, const N: usize, A: Allocator> IntoIterator for &'a mut SmallCompactVec<T, N, A> {
+    type Item = &'a mut T;
+    type IntoIter = ::std::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.deref_mut().into_iter()
+    }
+}
+
+impl<T: Compact, const N: usize, A: Allocator> Compact for SmallCompactVec<T, N, A> {
+    fn is_still_compact(&self) -> bool {
+        !self.is_inline && self.ptr.is_compact()
+            && self.iter().all(|elem| elem.is_still_compact())
+    }
+
+    fn dynamic_size_bytes(&self) -> usize {
+        // once compacted, only the live elements are kept around, so the
+        // compact layout is sized for `len`, not for `cap`/`N`
+        self.len() * ::std::mem::size_of::<T>()
+            + self.iter().map(|elem| elem.dynamic_size_bytes()).sum::<usize>()
+    }
+
+    unsafe fn compact_from(&mut self, source: &Self, new_dynamic_part: *mut u8) {
+        self.is_inline = false;
+        self.len = source.len;
+        self.cap = source.len;
+        self.ptr.set_to_compact(new_dynamic_part as *mut T);
+
+        let mut offset = self.cap * ::std::mem::size_of::<T>();
+
+        for (i, source_elem) in source.iter().enumerate() {
+            let elem_dynamic_part = new_dynamic_part.offset(offset as isize);
+            (*self.ptr.mut_ptr().offset(i as isize)).compact_from(source_elem, elem_dynamic_part);
+            offset += source_elem.dynamic_size_bytes();
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::std::cell::Cell;
+    use ::std::rc::Rc;
+
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn double_buf_preserves_live_elements_without_double_drop() {
+        let counter = Rc::new(Cell::new(0));
+        let mut vec: CompactVec<DropCounter> = CompactVec::with_capacity(1);
+        vec.push(DropCounter(counter.clone()));
+        vec.push(DropCounter(counter.clone())); // forces double_buf
+
+        assert_eq!(counter.get(), 0, "double_buf must not drop moved elements");
+        drop(vec);
+        assert_eq!(counter.get(), 2, "each live element should be dropped exactly once");
+    }
+
+    #[test]
+    fn reserve_preserves_live_elements_without_double_drop() {
+        let counter = Rc::new(Cell::new(0));
+        let mut vec: CompactVec<DropCounter> = CompactVec::with_capacity(1);
+        vec.push(DropCounter(counter.clone()));
+        vec.reserve(4); // forces growth via reserve, not double_buf
+
+        assert_eq!(counter.get(), 0, "reserve must not drop moved elements");
+        drop(vec);
+        assert_eq!(counter.get(), 1, "the live element should be dropped exactly once");
+    }
+
+    #[test]
+    fn clear_drops_all_elements() {
+        let counter = Rc::new(Cell::new(0));
+        let mut vec: CompactVec<DropCounter> = CompactVec::new();
+        for _ in 0..3 {
+            vec.push(DropCounter(counter.clone()));
+        }
+
+        vec.clear();
+
+        assert_eq!(counter.get(), 3);
+        assert_eq!(vec.len(), 0);
+    }
+
+    #[test]
+    fn truncate_drops_tail_elements() {
+        let counter = Rc::new(Cell::new(0));
+        let mut vec: CompactVec<DropCounter> = CompactVec::new();
+        for _ in 0..5 {
+            vec.push(DropCounter(counter.clone()));
+        }
+
+        vec.truncate(2);
+
+        assert_eq!(counter.get(), 3);
+        assert_eq!(vec.len(), 2);
+    }
+
+    #[test]
+    fn remove_returns_element_without_dropping_it() {
+        let counter = Rc::new(Cell::new(0));
+        let mut vec: CompactVec<DropCounter> = CompactVec::new();
+        for _ in 0..3 {
+            vec.push(DropCounter(counter.clone()));
+        }
+
+        let removed = vec.remove(0);
+        assert_eq!(counter.get(), 0);
+        assert_eq!(vec.len(), 2);
+
+        drop(removed);
+        assert_eq!(counter.get(), 1);
+    }
+
+    #[test]
+    fn swap_remove_moves_last_element_into_the_hole() {
+        let mut vec: CompactVec<u32> = CompactVec::new();
+        vec.push(1);
+        vec.push(2);
+        vec.push(3);
+
+        assert_eq!(vec.swap_remove(0), 1);
+        assert_eq!(&vec[..], &[3, 2]);
+    }
+
+    #[test]
+    fn retain_drops_filtered_elements() {
+        let counter = Rc::new(Cell::new(0));
+        let mut vec: CompactVec<(usize, DropCounter)> = CompactVec::new();
+        for i in 0..5 {
+            vec.push((i, DropCounter(counter.clone())));
+        }
+
+        vec.retain(|&(i, _)| i % 2 == 0);
+
+        assert_eq!(counter.get(), 2);
+        assert_eq!(vec.len(), 3);
+    }
+
+    #[test]
+    fn zst_vector_supports_push_and_pop() {
+        let mut vec: CompactVec<()> = CompactVec::new();
+        for _ in 0..1000 {
+            vec.push(());
+        }
+        assert_eq!(vec.len(), 1000);
+
+        for _ in 0..1000 {
+            assert_eq!(vec.pop(), Some(()));
+        }
+        assert_eq!(vec.pop(), None);
+    }
+
+    #[test]
+    fn nested_compact_round_trip() {
+        let mut inner_a: CompactVec<u32> = CompactVec::new();
+        inner_a.push(1);
+        inner_a.push(2);
+        let mut inner_b: CompactVec<u32> = CompactVec::new();
+        inner_b.push(3);
+
+        let mut source: CompactVec<CompactVec<u32>> = CompactVec::new();
+        source.push(inner_a);
+        source.push(inner_b);
+
+        let mut buffer = vec![0u8; source.dynamic_size_bytes()];
+        let mut compacted: CompactVec<CompactVec<u32>> = CompactVec::new();
+        unsafe {
+            compacted.compact_from(&source, buffer.as_mut_ptr());
+        }
+
+        assert!(compacted.is_still_compact());
+        assert_eq!(compacted.len(), 2);
+        assert_eq!(&compacted[0][..], &[1, 2]);
+        assert_eq!(&compacted[1][..], &[3]);
+
+        // the compacted copy's backing storage lives in `buffer`, not in
+        // an allocation owned by the allocator, so don't let its Drop run
+        ::std::mem::forget(compacted);
+    }
+
+    #[test]
+    fn clone_drops_already_cloned_prefix_on_panic() {
+        struct PanicsOnThirdClone {
+            index: usize,
+            counter: Rc<Cell<usize>>
+        }
+
+        impl Clone for PanicsOnThirdClone {
+            fn clone(&self) -> Self {
+                if self.index == 2 {
+                    panic!("boom");
+                }
+                PanicsOnThirdClone {index: self.index, counter: self.counter.clone()}
+            }
+        }
+
+        impl Drop for PanicsOnThirdClone {
+            fn drop(&mut self) {
+                self.counter.set(self.counter.get() + 1);
+            }
+        }
+
+        let counter = Rc::new(Cell::new(0));
+        let mut vec: CompactVec<PanicsOnThirdClone> = CompactVec::new();
+        for i in 0..4 {
+            vec.push(PanicsOnThirdClone {index: i, counter: counter.clone()});
+        }
+
+        let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| vec.clone()));
+
+        assert!(result.is_err());
+        // the two elements cloned before the panic must have been dropped
+        // by the guard, not leaked
+        assert_eq!(counter.get(), 2);
+    }
+
+    #[test]
+    fn small_compact_vec_migrates_from_inline_to_heap() {
+        let mut vec: SmallCompactVec<u32, 2> = SmallCompactVec::new();
+        vec.push(1);
+        vec.push(2);
+        assert_eq!(vec.len(), 2);
+
+        vec.push(3); // forces migration to the heap
+
+        assert_eq!(&vec[..], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn reserve_then_push_fills_without_further_growth() {
+        let mut vec: CompactVec<u32> = CompactVec::new();
+        vec.reserve(10);
+
+        for i in 0..10 {
+            vec.push(i);
+        }
+
+        assert_eq!(&vec[..], &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn extend_from_slice_appends_copy_elements() {
+        let mut vec: CompactVec<u32> = CompactVec::new();
+        vec.push(1);
+
+        vec.extend_from_slice(&[2, 3, 4]);
+
+        assert_eq!(&vec[..], &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn extend_and_from_iter_collect_elements() {
+        let vec: CompactVec<u32> = (0..5).collect();
+        assert_eq!(&vec[..], &[0, 1, 2, 3, 4]);
+
+        let mut vec = CompactVec::new();
+        vec.push(10);
+        vec.extend(vec![20, 30]);
+
+        assert_eq!(&vec[..], &[10, 20, 30]);
+    }
+
+    #[test]
+    fn append_moves_elements_and_empties_other_without_double_drop() {
+        let counter = Rc::new(Cell::new(0));
+        let mut a: CompactVec<DropCounter> = CompactVec::new();
+        a.push(DropCounter(counter.clone()));
+
+        let mut b: CompactVec<DropCounter> = CompactVec::new();
+        b.push(DropCounter(counter.clone()));
+        b.push(DropCounter(counter.clone()));
+
+        a.append(&mut b);
+
+        assert_eq!(a.len(), 3);
+        assert_eq!(b.len(), 0);
+        assert_eq!(counter.get(), 0, "appended elements must not be dropped while moved");
+
+        drop(a);
+        drop(b);
+        assert_eq!(counter.get(), 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_preserves_contents() {
+        let mut vec: CompactVec<u32> = CompactVec::new();
+        vec.push(1);
+        vec.push(2);
+        vec.push(3);
+
+        let json = ::serde_json::to_string(&vec).unwrap();
+        let decoded: CompactVec<u32> = ::serde_json::from_str(&json).unwrap();
+
+        assert_eq!(&decoded[..], &vec[..]);
+    }
+}